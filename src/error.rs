@@ -0,0 +1,43 @@
+//! Error types returned by the non-panicking `try_`-prefixed methods on
+//! `WorkQueue` and `SyncFlag`.
+
+use std::error::Error;
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+
+/// Locks `mutex`, recovering the guard if it was poisoned by a panic in
+/// another thread rather than propagating the poison. Returns whether the
+/// mutex was in fact poisoned, so `try_`-prefixed callers can still report
+/// it to the caller even though the operation itself goes ahead.
+///
+/// Shared by `work_queue` and `sync_flag`, whose `try_`-prefixed methods
+/// both need this same poison-then-recover behavior.
+pub(crate) fn lock_recovering<G>(mutex: &Mutex<G>) -> (MutexGuard<'_, G>, bool) {
+    match mutex.lock() {
+        Ok(guard) => (guard, false),
+        Err(poisoned) => (poisoned.into_inner(), true),
+    }
+}
+
+/// An error returned by a `try_`-prefixed method in place of panicking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkQueueError {
+    /// The underlying mutex was poisoned by a panic in another thread.
+    ///
+    /// The protected data has already been recovered and the queue or flag
+    /// remains perfectly usable; this variant exists purely so a caller who
+    /// wants to know can notice and log that some other thread panicked.
+    Poisoned,
+}
+
+impl fmt::Display for WorkQueueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorkQueueError::Poisoned => {
+                write!(f, "the underlying mutex was poisoned by a panic in another thread")
+            }
+        }
+    }
+}
+
+impl Error for WorkQueueError {}
@@ -10,9 +10,9 @@
 //! This is somewhat more complex than is required for processing a list of numbers, but
 //! it illustrates the principle. When looking at this example, imagine that you might
 //! 
-//! * have a mechanism by which some of the worker threads can add new work or, 
+//! * have a mechanism by which some of the worker threads can add new work or,
 //! * that the control thread (or another thread) expects to produce work _forever_,
-//! as in a server, for instance. 
+//!   as in a server, for instance.
 //!
 //! The `SyncFlag` can then be used at any future time to
 //! gracefully shut down all the worker threads, e.g. when the controller gets 
@@ -45,17 +45,14 @@
 //!     let t_more_jobs = more_jobs_rx.clone();
 //!
 //!     let handle = thread::spawn(move || {
-//!         // Loop until the controller says to stop.
-//!         while t_more_jobs.get() {
-//!             // Try to get a piece of work to do.
-//!             if let Some(work_input) = t_queue.pull_work() {
-//!                 // Do some work. Totally contrived in this case.
-//!                 let result = work_input % 1024;
-//!                 // Send the results of the work to the main thread. 
-//!                 t_results_tx.send((work_input, result)).unwrap();
-//!             } else {
-//!                 thread::yield_now();
-//!             }
+//!         // Block until either work is available or the controller says to
+//!         // stop, parking on a condvar instead of spinning on `pull_work`
+//!         // and `more_jobs.get()`.
+//!         while let Some(work_input) = t_queue.wait(&t_more_jobs) {
+//!             // Do some work. Totally contrived in this case.
+//!             let result = work_input % 1024;
+//!             // Send the results of the work to the main thread.
+//!             t_results_tx.send((work_input, result)).unwrap();
 //!         }
 //!     });
 //!     
@@ -100,6 +97,15 @@
 pub mod work_queue;
 pub use work_queue::WorkQueue;
 
+mod stealing;
+pub use stealing::StealingWorker;
+
+pub mod error;
+pub use error::WorkQueueError;
+
 pub mod sync_flag;
 pub use sync_flag::new_syncflag;
 
+pub mod work_controller;
+pub use work_controller::{WorkController, WorkControllerBuilder};
+
@@ -0,0 +1,204 @@
+//! A work-stealing backend for `WorkQueue`, for use when the single shared
+//! mutex behind the default queue becomes a bottleneck under high worker
+//! counts. Modeled on rayon-core's registry: a global `Injector` takes work
+//! submitted from outside the pool, and each worker thread owns a local
+//! deque plus the `Stealer` handles of its peers, so most pulls never touch
+//! shared state at all.
+//!
+//! This is opt-in; `WorkQueue::new()` still builds the plain mutex-backed
+//! queue from `work_queue`, so existing users are unaffected.
+//!
+//! # Examples
+//!
+//! Each `StealingWorker` checks its own local deque first, then the shared
+//! injector, then its peers, in that order.
+//!
+//! ```
+//! use workctl::WorkQueue;
+//!
+//! let (mut wq, mut workers) = WorkQueue::new_stealing(2);
+//! let worker1 = workers.pop().unwrap();
+//! let worker0 = workers.pop().unwrap();
+//!
+//! // A worker prefers work on its own local deque first.
+//! worker0.push_work(1);
+//! assert_eq!(worker0.pull_work(), Some(1));
+//!
+//! // With nothing local, it steals a batch from the shared injector, which
+//! // `WorkQueue::push_work` submits into from outside the pool.
+//! wq.push_work(2);
+//! assert_eq!(worker0.pull_work(), Some(2));
+//!
+//! // With the injector empty too, it steals straight from a peer.
+//! worker1.push_work(3);
+//! assert_eq!(worker0.pull_work(), Some(3));
+//! ```
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use crossbeam_deque::{Injector, Steal, Stealer, Worker};
+
+/// The shared state behind a work-stealing `WorkQueue`: the injector that
+/// external callers push into, and the `Stealer` handle for every worker's
+/// local deque, so any worker can steal from any other.
+pub(crate) struct StealingQueue<T> {
+    pub(crate) injector: Injector<T>,
+    pub(crate) stealers: Vec<Stealer<T>>,
+    /// Paired with a dummy `Mutex<()>` purely so `WorkQueue::pull_work_blocking`
+    /// and `WorkQueue::wait` can park on a condvar instead of spinning on
+    /// `pull_work`, the way the mutex backend parks on its own condvar.
+    /// Notified whenever work is pushed anywhere in the pool (the injector
+    /// or any worker's local deque). The `WorkQueue` pulls that park here
+    /// only take from the injector, so a wakeup after a local-deque push
+    /// finds nothing for them and they park again.
+    pub(crate) notify: (Mutex<()>, Condvar),
+}
+
+impl<T> StealingQueue<T> {
+    pub(crate) fn push(&self, work_element: T) {
+        self.injector.push(work_element);
+        self.notify_parked();
+    }
+
+    /// Wakes every thread parked in `park`. Called after any push, whether
+    /// to the injector or to a worker's local deque.
+    pub(crate) fn notify_parked(&self) {
+        let guard = self.notify.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        self.notify.1.notify_all();
+        drop(guard);
+    }
+
+    /// Blocks the calling thread for up to `timeout`, or until the next
+    /// `notify_parked`, whichever comes first. Used by `pull_work_blocking`
+    /// and `wait` to avoid busy-spinning between failed `pull_work` attempts.
+    pub(crate) fn park(&self, timeout: Duration) {
+        let guard = self.notify.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let _ = self.notify.1.wait_timeout(guard, timeout);
+    }
+}
+
+/// One worker's handle onto a work-stealing queue: its own local deque,
+/// plus a shared view of the injector and every peer's `Stealer`.
+///
+/// Unlike `WorkQueue`, a `StealingWorker` is not `Clone` and is not meant to
+/// be shared across threads; each worker thread in the pool gets exactly
+/// one, created for it by `WorkQueue::new_stealing`.
+pub struct StealingWorker<T: Send> {
+    pub(crate) local: Worker<T>,
+    pub(crate) shared: Arc<StealingQueue<T>>,
+    pub(crate) index: usize,
+}
+
+impl<T: Send> StealingWorker<T> {
+    /// Pushes work onto this worker's own local deque. The push itself is
+    /// lock-free, but it then briefly locks the pool's notify mutex to wake
+    /// any parked thread; only this worker thread may call it.
+    pub fn push_work(&self, work_element: T) {
+        self.local.push(work_element);
+        self.shared.notify_parked();
+    }
+
+    /// Pulls a piece of work, preferring (in order): this worker's own
+    /// local deque (LIFO, cache-friendly), a batch stolen from the shared
+    /// injector, then a single item stolen from a peer, round-robining
+    /// across peers. Returns `None` only once all three sources agree
+    /// there is nothing left.
+    pub fn pull_work(&self) -> Option<T> {
+        if let Some(work) = self.local.pop() {
+            return Some(work);
+        }
+
+        loop {
+            match self.shared.injector.steal_batch_and_pop(&self.local) {
+                Steal::Success(work) => return Some(work),
+                Steal::Retry => continue,
+                Steal::Empty => break,
+            }
+        }
+
+        let num_peers = self.shared.stealers.len();
+        'round_robin: for offset in 0..num_peers {
+            let peer = (self.index + offset) % num_peers;
+            if peer == self.index {
+                continue;
+            }
+            loop {
+                match self.shared.stealers[peer].steal() {
+                    Steal::Success(work) => return Some(work),
+                    Steal::Retry => continue,
+                    Steal::Empty => continue 'round_robin,
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::work_queue::WorkQueue;
+    use std::collections::HashSet;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    /// Spawns several workers that each push a batch of distinct items and
+    /// then race each other to drain the pool (local deque, injector, and
+    /// peers all at once), checking that every item is retrieved exactly
+    /// once despite the concurrent stealing.
+    #[test]
+    fn concurrent_push_and_steal_delivers_each_item_once() {
+        const NUM_WORKERS: usize = 4;
+        const ITEMS_PER_WORKER: usize = 200;
+
+        let (wq, workers) = WorkQueue::<usize>::new_stealing(NUM_WORKERS);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+
+        let handles: Vec<_> = workers
+            .into_iter()
+            .enumerate()
+            .map(|(worker_index, worker)| {
+                let seen = seen.clone();
+                thread::spawn(move || {
+                    let base = worker_index * ITEMS_PER_WORKER;
+                    for offset in 0..ITEMS_PER_WORKER {
+                        worker.push_work(base + offset);
+                    }
+
+                    let mut local_seen = Vec::new();
+                    while let Some(work) = worker.pull_work() {
+                        local_seen.push(work);
+                    }
+                    seen.lock().unwrap().extend(local_seen);
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        // Workers only push onto their own local deques and each drains its
+        // own deque before exiting, so the shared injector should be empty;
+        // drain it anyway so anything that did end up there is still counted.
+        let mut wq = wq;
+        while let Some(work) = wq.pull_work() {
+            seen.lock().unwrap().push(work);
+        }
+
+        let seen = seen.lock().unwrap();
+        let expected: HashSet<usize> = (0..NUM_WORKERS * ITEMS_PER_WORKER).collect();
+        let actual: HashSet<usize> = seen.iter().copied().collect();
+
+        assert_eq!(
+            seen.len(),
+            actual.len(),
+            "Expected every item to be retrieved exactly once, with none duplicated."
+        );
+        assert_eq!(
+            actual, expected,
+            "Expected every pushed item to be retrieved exactly once, with none lost."
+        );
+    }
+}
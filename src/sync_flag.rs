@@ -13,18 +13,17 @@
 //! ```
 //! use workctl::new_syncflag;
 //! use std::thread;
-//! 
+//!
 //! // Create a new SyncFlag set to communicate with the spawned thread.
 //! let (mut tx, rx) = new_syncflag(true);
-//! 
+//!
 //! // This isn't technically needed in this case, but if we were spawning more
 //! // than one thread we'd create a clone for each.
 //! let thread_rx = rx.clone();
 //! thread::spawn(move || {
-//!     // Do nothing as long as the sync flag is true. Really, you'd do work here.
-//!     while thread_rx.get() {
-//!         thread::yield_now();
-//!     }
+//!     // Block until the controller sets the flag to false, rather than
+//!     // spinning on `get()`.
+//!     thread_rx.wait_until(false);
 //!     println!("Thread got signal to close.");
 //! });
 //!
@@ -34,7 +33,10 @@
 //! tx.set(false);
 //! ```
 
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use crate::error::{lock_recovering, WorkQueueError};
 
 /// `SyncFlagTx` is the transmitting (mutable) half of a Single Producer,
 /// Multiple Consumer Boolean (e.g. the opposite of `std::sync::mpsc`).
@@ -43,24 +45,47 @@ use std::sync::{Arc, Mutex};
 ///
 /// `SyncFlagTx` is not Clone because it should only exist in one place.
 ///
-/// # Panics
-/// The functions on this type will panic if the underlying mutex became poisoned; 
-/// that is, if there was a panic during the execution of any mutex-acquiring 
-/// function. This is pretty unlikely.
+/// # Poisoning
+/// If the underlying mutex becomes poisoned (because some other thread
+/// holding it panicked), the functions on this type recover the protected
+/// `bool` rather than panicking themselves; the flag is still perfectly
+/// usable either way. Use `try_set` if you want to be told when that
+/// happened.
 pub struct SyncFlagTx {
-    inner: Arc<Mutex<bool>>,
+    inner: Arc<(Mutex<bool>, Condvar)>,
 }
 
 impl SyncFlagTx {
     /// Sets the interior value of the `SyncFlagTx` which will be read by any
-    /// `SyncFlagRx` that exist for this SyncFlag.
+    /// `SyncFlagRx` that exist for this SyncFlag, then wakes up every
+    /// receiver blocked in `wait_until` or `wait_until_timeout`.
     pub fn set(&mut self, state: bool) {
-        if let Ok(mut v) = self.inner.lock() {
-            // The * (deref operator) means assigning to what's inside the
-            // MutexGuard, not the guard itself (which would be silly)
-            *v = state;
+        let (mutex, condvar) = &*self.inner;
+        let (mut v, _poisoned) = lock_recovering(mutex);
+        // The * (deref operator) means assigning to what's inside the
+        // MutexGuard, not the guard itself (which would be silly)
+        *v = state;
+        drop(v);
+        condvar.notify_all();
+    }
+
+    /// Like `set`, but reports mutex poisoning instead of silently
+    /// recovering from it.
+    ///
+    /// The set itself still goes ahead either way; `Err(WorkQueueError::Poisoned)`
+    /// means some other thread holding the flag's mutex panicked, not that
+    /// this call failed to run.
+    pub fn try_set(&mut self, state: bool) -> Result<(), WorkQueueError> {
+        let (mutex, condvar) = &*self.inner;
+        let (mut v, poisoned) = lock_recovering(mutex);
+        *v = state;
+        drop(v);
+        condvar.notify_all();
+
+        if poisoned {
+            Err(WorkQueueError::Poisoned)
         } else {
-            panic!("SyncFlagTx::set() tried to lock a poisoned mutex.");
+            Ok(())
         }
     }
 }
@@ -72,38 +97,179 @@ impl SyncFlagTx {
 ///
 /// `SyncFlagRx` is Clone so it can be shared across threads.
 ///
-/// # Panics
-/// The functions on this type will panic if the underlying mutex became poisoned; 
-/// that is, if there was a panic during the execution of any mutex-acquiring 
-/// function. This is pretty unlikely.
-
+/// # Poisoning
+/// If the underlying mutex becomes poisoned (because some other thread
+/// holding it panicked), the functions on this type recover the protected
+/// `bool` rather than panicking themselves; the flag is still perfectly
+/// usable either way. Use `try_get` if you want to be told when that
+/// happened.
 #[derive(Clone)]
 pub struct SyncFlagRx {
-    inner: Arc<Mutex<bool>>,
+    inner: Arc<(Mutex<bool>, Condvar)>,
 }
 
 impl SyncFlagRx {
     /// Gets the interior state of the `SyncFlagRx` to whatever the corresponding
     /// `SyncFlagTx` last set it to.
-    ///
-    /// # Errors
-    /// If the underlying mutex is poisoned this might return an error.
     pub fn get(&self) -> bool {
-        if let Ok(v) = self.inner.lock() {
-            // Deref the MutexGuard to get at the bool inside
-            *v
+        let (mutex, _condvar) = &*self.inner;
+        let (v, _poisoned) = lock_recovering(mutex);
+        // Deref the MutexGuard to get at the bool inside
+        *v
+    }
+
+    /// Like `get`, but reports mutex poisoning instead of silently
+    /// recovering from it.
+    ///
+    /// The read itself still goes ahead either way; `Err(WorkQueueError::Poisoned)`
+    /// means some other thread holding the flag's mutex panicked, not that
+    /// this call failed to run.
+    pub fn try_get(&self) -> Result<bool, WorkQueueError> {
+        let (mutex, _condvar) = &*self.inner;
+        let (v, poisoned) = lock_recovering(mutex);
+        if poisoned {
+            Err(WorkQueueError::Poisoned)
         } else {
-            panic!("SyncFlagRx::get() tried to lock a poisoned mutex.");
+            Ok(*v)
+        }
+    }
+
+    /// Blocks the current thread until the flag's value equals `target`,
+    /// parking on a condition variable instead of spinning on `get()`.
+    ///
+    /// Returns immediately if the flag already holds `target`.
+    pub fn wait_until(&self, target: bool) {
+        let (mutex, condvar) = &*self.inner;
+        let (mut v, _poisoned) = lock_recovering(mutex);
+        while *v != target {
+            v = condvar.wait(v).unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+
+    /// Like `wait_until`, but gives up after `timeout` has elapsed.
+    ///
+    /// Returns `true` if the flag reached `target` before the timeout, or
+    /// `false` if the timeout elapsed first.
+    pub fn wait_until_timeout(&self, target: bool, timeout: Duration) -> bool {
+        let (mutex, condvar) = &*self.inner;
+        let (mut v, _poisoned) = lock_recovering(mutex);
+        let mut remaining = timeout;
+        loop {
+            if *v == target {
+                return true;
+            }
+
+            let started = std::time::Instant::now();
+            let (new_v, result) = condvar
+                .wait_timeout(v, remaining)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            v = new_v;
+
+            if *v == target {
+                return true;
+            }
+            if result.timed_out() {
+                return false;
+            }
+
+            // Woken spuriously (or by a `set` to some other value) before
+            // the timeout elapsed; wait again for whatever time is left.
+            remaining = remaining.saturating_sub(started.elapsed());
+            if remaining.is_zero() {
+                return false;
+            }
         }
     }
 }
 
-/// Create a new `SyncFlagTx` and `SyncFlagRx` that can be used to share a bool 
+/// Create a new `SyncFlagTx` and `SyncFlagRx` that can be used to share a bool
 /// across a number of threads.
 pub fn new_syncflag(initial_state: bool) -> (SyncFlagTx, SyncFlagRx) {
-    let state = Arc::new(Mutex::new(initial_state));
+    let state = Arc::new((Mutex::new(initial_state), Condvar::new()));
     let tx = SyncFlagTx { inner: state.clone() };
     let rx = SyncFlagRx { inner: state.clone() };
 
-    return (tx, rx);
+    (tx, rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::new_syncflag;
+    use crate::error::WorkQueueError;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn recovers_from_poisoned_mutex() {
+        let (_tx, rx) = new_syncflag(true);
+
+        // Reach into the private mutex and poison it by panicking while the
+        // guard is held, the way a real panicking thread would.
+        let inner = rx.inner.clone();
+        let result = thread::spawn(move || {
+            let _guard = inner.0.lock().unwrap();
+            panic!("simulated panic while holding the flag's mutex");
+        })
+        .join();
+        assert!(result.is_err(), "Expected the spawned thread to panic.");
+
+        assert_eq!(
+            rx.try_get(),
+            Err(WorkQueueError::Poisoned),
+            "Expected try_get to report that the mutex was poisoned."
+        );
+        // The mutex stays poisoned for the rest of its life once poisoned
+        // (recovering a guard doesn't clear that), so this also reports
+        // Poisoned even though the flag itself keeps working normally.
+        assert_eq!(
+            rx.try_get(),
+            Err(WorkQueueError::Poisoned),
+            "Expected try_get to keep reporting the mutex as poisoned."
+        );
+        assert!(
+            rx.get(),
+            "Expected the flag to have recovered and kept its last value."
+        );
+    }
+
+    #[test]
+    fn wait_until_wakes_on_set() {
+        let (mut tx, rx) = new_syncflag(false);
+        let thread_rx = rx.clone();
+
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            tx.set(true);
+        });
+
+        // This blocks until the spawned thread's set() wakes it via the
+        // condvar, rather than spinning until the flag flips.
+        thread_rx.wait_until(true);
+        assert!(rx.get(), "Expected the flag to have been set to true.");
+
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn wait_until_timeout_reports_timeout_and_success() {
+        let (mut tx, rx) = new_syncflag(false);
+
+        assert!(
+            !rx.wait_until_timeout(true, Duration::from_millis(50)),
+            "Expected wait_until_timeout to time out when nobody sets the flag."
+        );
+
+        let thread_rx = rx.clone();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            tx.set(true);
+        });
+
+        assert!(
+            thread_rx.wait_until_timeout(true, Duration::from_secs(5)),
+            "Expected wait_until_timeout to succeed once the flag was set in time."
+        );
+
+        handle.join().unwrap();
+    }
 }
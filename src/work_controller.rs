@@ -0,0 +1,340 @@
+//! `WorkController` wires together a `WorkQueue`, a `SyncFlag`, and an
+//! `mpsc` results channel into a ready-to-use controller/worker pool, so
+//! callers don't have to hand-assemble those primitives every time.
+//!
+//! # Examples
+//!
+//! ```
+//! use workctl::WorkController;
+//!
+//! // Spawn a pool that squares every number we give it.
+//! let mut controller = WorkController::with_workers(4, |n: i32| n * n);
+//!
+//! for n in 0..10 {
+//!     controller.push_work(n);
+//! }
+//!
+//! let mut results: Vec<i32> = Vec::new();
+//! for _ in 0..10 {
+//!     results.push(controller.results_rx().recv().unwrap());
+//! }
+//! results.sort();
+//!
+//! assert_eq!(results, vec![0, 1, 4, 9, 16, 25, 36, 49, 64, 81]);
+//!
+//! // Dropping (or explicitly joining) the controller stops every worker.
+//! controller.join();
+//! ```
+//!
+//! Use `WorkControllerBuilder` instead of `WorkController::new`/`with_workers`
+//! when you need named threads, a custom stack size, or lifecycle hooks
+//! that run as each worker starts, exits, or catches a panic out of `job`.
+
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use crate::sync_flag::{new_syncflag, SyncFlagTx};
+use crate::work_queue::WorkQueue;
+
+/// Owns and manages a fixed-size pool of worker threads, each pulling work
+/// from a shared `WorkQueue`, running it through a user-supplied job
+/// closure, and sending the result back over an `mpsc` channel.
+///
+/// Dropping a `WorkController` (or calling `join`) sets the pool's shutdown
+/// `SyncFlag` to false and joins every worker thread, so no thread is ever
+/// left detached once the controller that owns it goes away.
+pub struct WorkController<T: Send, R: Send> {
+    queue: WorkQueue<T>,
+    results_rx: Receiver<R>,
+    run_flag: SyncFlagTx,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl<T: Send + 'static, R: Send + 'static> WorkController<T, R> {
+    /// Creates a new `WorkController`, spawning one worker thread per
+    /// logical CPU. Each worker runs `job` on every piece of work it pulls
+    /// and sends the result back over the results channel.
+    pub fn new<F>(job: F) -> Self
+    where
+        F: Fn(T) -> R + Send + Sync + 'static,
+    {
+        Self::with_workers(num_cpus::get(), job)
+    }
+
+    /// Creates a new `WorkController` with exactly `num_workers` worker
+    /// threads, each running `job` on every piece of work it pulls and
+    /// sending the result back over the results channel.
+    pub fn with_workers<F>(num_workers: usize, job: F) -> Self
+    where
+        F: Fn(T) -> R + Send + Sync + 'static,
+    {
+        WorkControllerBuilder::new().num_workers(num_workers).build(job)
+    }
+}
+
+impl<T: Send, R: Send> WorkController<T, R> {
+    /// Adds a piece of work to the pool's queue, to be picked up by
+    /// whichever worker thread is next free.
+    ///
+    /// Returns the number of elements in the queue after inserting that work.
+    pub fn push_work(&mut self, work_element: T) -> usize {
+        self.queue.push_work(work_element)
+    }
+
+    /// Returns a reference to the receiving half of the results channel, so
+    /// callers can collect results as workers produce them.
+    pub fn results_rx(&self) -> &Receiver<R> {
+        &self.results_rx
+    }
+
+    /// Tells every worker to stop pulling new work once the queue is
+    /// drained, then blocks until all of them have exited.
+    ///
+    /// This happens automatically when the `WorkController` is dropped;
+    /// call `join` explicitly when you want to wait for shutdown to finish
+    /// before moving on.
+    pub fn join(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.run_flag.set(false);
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl<T: Send, R: Send> Drop for WorkController<T, R> {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+type StartHandler = dyn Fn(usize) + Send + Sync;
+type ExitHandler = dyn Fn(usize) + Send + Sync;
+type PanicHandler = dyn Fn(Box<dyn Any + Send>) + Send + Sync;
+
+/// Builds a `WorkController`, letting callers configure the worker count,
+/// thread naming, stack size, and lifecycle hooks the way `std::thread::Builder`
+/// and rayon-core's registry do.
+///
+/// # Examples
+///
+/// ```
+/// use workctl::WorkControllerBuilder;
+///
+/// let mut controller = WorkControllerBuilder::new()
+///     .num_workers(2)
+///     .thread_name(|i| format!("my-worker-{}", i))
+///     .on_start(|i| println!("worker {} starting up", i))
+///     .on_panic(|_| eprintln!("a job panicked; worker keeps going"))
+///     .build(|n: i32| n * 2);
+///
+/// controller.push_work(21);
+/// assert_eq!(controller.results_rx().recv().unwrap(), 42);
+/// controller.join();
+/// ```
+pub struct WorkControllerBuilder<T: Send, R: Send> {
+    num_workers: Option<usize>,
+    thread_name: Option<Arc<dyn Fn(usize) -> String + Send + Sync>>,
+    stack_size: Option<usize>,
+    on_start: Option<Arc<StartHandler>>,
+    on_exit: Option<Arc<ExitHandler>>,
+    on_panic: Option<Arc<PanicHandler>>,
+    _marker: std::marker::PhantomData<fn() -> (T, R)>,
+}
+
+impl<T: Send + 'static, R: Send + 'static> WorkControllerBuilder<T, R> {
+    /// Creates a new builder with no workers configured yet; defaults to
+    /// one worker thread per logical CPU if `num_workers` is never called.
+    pub fn new() -> Self {
+        Self {
+            num_workers: None,
+            thread_name: None,
+            stack_size: None,
+            on_start: None,
+            on_exit: None,
+            on_panic: None,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Sets how many worker threads to spawn. Defaults to the machine's
+    /// logical CPU count.
+    pub fn num_workers(mut self, num_workers: usize) -> Self {
+        self.num_workers = Some(num_workers);
+        self
+    }
+
+    /// Sets a function used to name each worker thread, called once per
+    /// worker with its index (`0..num_workers`).
+    pub fn thread_name<F>(mut self, thread_name: F) -> Self
+    where
+        F: Fn(usize) -> String + Send + Sync + 'static,
+    {
+        self.thread_name = Some(Arc::new(thread_name));
+        self
+    }
+
+    /// Sets the stack size, in bytes, for every worker thread.
+    pub fn stack_size(mut self, stack_size: usize) -> Self {
+        self.stack_size = Some(stack_size);
+        self
+    }
+
+    /// Registers a callback run once inside each worker thread, before its
+    /// first pull from the queue. Useful for setting up per-thread state
+    /// like scratch buffers or thread-local connections.
+    pub fn on_start<F>(mut self, on_start: F) -> Self
+    where
+        F: Fn(usize) + Send + Sync + 'static,
+    {
+        self.on_start = Some(Arc::new(on_start));
+        self
+    }
+
+    /// Registers a callback run once inside each worker thread as it winds
+    /// down, after the shutdown flag is set and the queue is drained.
+    pub fn on_exit<F>(mut self, on_exit: F) -> Self
+    where
+        F: Fn(usize) + Send + Sync + 'static,
+    {
+        self.on_exit = Some(Arc::new(on_exit));
+        self
+    }
+
+    /// Registers a callback run whenever `job` panics. The panic is caught
+    /// with `catch_unwind` so the worker logs it (via this callback) and
+    /// keeps pulling work instead of taking the whole thread down.
+    pub fn on_panic<F>(mut self, on_panic: F) -> Self
+    where
+        F: Fn(Box<dyn Any + Send>) + Send + Sync + 'static,
+    {
+        self.on_panic = Some(Arc::new(on_panic));
+        self
+    }
+
+    /// Builds the `WorkController`, spawning every configured worker
+    /// thread with `job` as its work function.
+    pub fn build<F>(self, job: F) -> WorkController<T, R>
+    where
+        F: Fn(T) -> R + Send + Sync + 'static,
+    {
+        let num_workers = self.num_workers.unwrap_or_else(num_cpus::get);
+        let queue = WorkQueue::new();
+        let (results_tx, results_rx) = channel();
+        let (run_tx, run_rx) = new_syncflag(true);
+        let job = Arc::new(job);
+
+        let mut handles = Vec::with_capacity(num_workers);
+        for index in 0..num_workers {
+            let mut worker_queue = queue.clone();
+            let worker_run_rx = run_rx.clone();
+            let worker_results_tx = results_tx.clone();
+            let worker_job = job.clone();
+            let on_start = self.on_start.clone();
+            let on_exit = self.on_exit.clone();
+            let on_panic = self.on_panic.clone();
+
+            let mut builder = thread::Builder::new();
+            if let Some(thread_name) = &self.thread_name {
+                builder = builder.name(thread_name(index));
+            }
+            if let Some(stack_size) = self.stack_size {
+                builder = builder.stack_size(stack_size);
+            }
+
+            let handle = builder
+                .spawn(move || {
+                    if let Some(on_start) = &on_start {
+                        on_start(index);
+                    }
+
+                    loop {
+                        // `wait` returns `None` as soon as the shutdown flag
+                        // is seen, so finish whatever is still queued before
+                        // exiting.
+                        let work = match worker_queue.wait(&worker_run_rx) {
+                            Some(work) => work,
+                            None => match worker_queue.pull_work() {
+                                Some(work) => work,
+                                None => break,
+                            },
+                        };
+                        let result = panic::catch_unwind(AssertUnwindSafe(|| worker_job(work)));
+                        match result {
+                            Ok(result) => {
+                                if worker_results_tx.send(result).is_err() {
+                                    // No one is listening for results
+                                    // anymore; stop rather than keep
+                                    // producing work nobody will collect.
+                                    break;
+                                }
+                            }
+                            Err(payload) => {
+                                if let Some(on_panic) = &on_panic {
+                                    on_panic(payload);
+                                }
+                            }
+                        }
+                    }
+
+                    if let Some(on_exit) = &on_exit {
+                        on_exit(index);
+                    }
+                })
+                .expect("failed to spawn WorkController worker thread");
+
+            handles.push(handle);
+        }
+
+        WorkController { queue, results_rx, run_flag: run_tx, handles }
+    }
+}
+
+impl<T: Send + 'static, R: Send + 'static> Default for WorkControllerBuilder<T, R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WorkControllerBuilder;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn on_panic_fires_and_worker_keeps_going() {
+        let (panics_tx, panics_rx) = channel();
+
+        let mut controller = WorkControllerBuilder::new()
+            .num_workers(1)
+            .on_panic(move |_payload| {
+                panics_tx.send(()).unwrap();
+            })
+            .build(|n: i32| {
+                if n == 0 {
+                    panic!("simulated job panic");
+                }
+                n * 2
+            });
+
+        controller.push_work(0);
+        controller.push_work(21);
+
+        panics_rx
+            .recv()
+            .expect("Expected on_panic to fire for the panicking job.");
+        assert_eq!(
+            controller.results_rx().recv().unwrap(),
+            42,
+            "Expected the worker to keep pulling and processing work after the panic."
+        );
+
+        controller.join();
+    }
+}
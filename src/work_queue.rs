@@ -1,12 +1,46 @@
 //! `WorkQueue` is a purely safe work queue, suitable for fairly distributing
 //! work to any number of worker threads from any number of controllers.
 
-use std::sync::{Mutex, Arc};
+use std::sync::{Mutex, Condvar, Arc};
 use std::collections::VecDeque;
-use std::thread;
+use std::time::Duration;
 
+use crossbeam_deque::{Injector, Steal, Worker as StealerDeque};
+
+use crate::error::{lock_recovering, WorkQueueError};
+use crate::stealing::{StealingQueue, StealingWorker};
 use crate::sync_flag::SyncFlagRx;
 
+/// How long `wait` blocks on the condvar before waking up to re-check the
+/// `SyncFlagRx` it was given. This keeps `wait` interruptible without
+/// busy-polling.
+const WAIT_RECHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The internal storage backing a `WorkQueue`: either the default
+/// mutex+condvar deque, or the work-stealing setup built by `new_stealing`.
+/// Private; see `WorkQueue`'s own docs for the public-facing behavior.
+enum Backend<T: Send> {
+    /// The default backend: a single shared deque behind a mutex, woken up
+    /// via a condvar instead of spinning.
+    Mutex(Arc<(Mutex<VecDeque<T>>, Condvar)>),
+    /// The work-stealing backend built by `new_stealing`: external callers
+    /// (anyone holding a `WorkQueue` rather than a `StealingWorker`) submit
+    /// straight into the shared injector.
+    Stealing(Arc<StealingQueue<T>>),
+}
+
+// Implemented by hand rather than derived: `Backend<T>` only ever clones the
+// `Arc` pointing at the shared state, never a `T`, so it shouldn't require
+// `T: Clone` the way a derived impl would.
+impl<T: Send> Clone for Backend<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Backend::Mutex(inner) => Backend::Mutex(inner.clone()),
+            Backend::Stealing(inner) => Backend::Stealing(inner.clone()),
+        }
+    }
+}
+
 /// A generic work queue for any work element that is Send.
 /// This queue is symmetric, in that any thread with a copy of it can
 /// add work or remove work.
@@ -44,24 +78,67 @@ use crate::sync_flag::SyncFlagRx;
 /// handle.join().unwrap();
 /// ```
 ///
-/// # Panics
-/// The functions on this type will panic if the underlying mutex became poisoned;
-/// that is, if there was a panic during the execution of any mutex-acquiring function.
-/// This is pretty unlikely.
-#[derive(Clone)]
+/// # Poisoning
+/// If the underlying mutex becomes poisoned (because some other thread
+/// holding it panicked), the functions on this type recover the protected
+/// `VecDeque` rather than panicking themselves; a panic in one worker
+/// shouldn't wedge the queue for everyone else. Use the `try_`-prefixed
+/// methods if you want to be told when that happened.
 pub struct WorkQueue<T: Send> {
-    inner: Arc<Mutex<VecDeque<T>>>,
+    inner: Backend<T>,
+}
+
+// See the note on `Backend`'s `Clone` impl: this is hand-written so cloning
+// a `WorkQueue<T>` doesn't require `T: Clone`.
+impl<T: Send> Clone for WorkQueue<T> {
+    fn clone(&self) -> Self {
+        Self { inner: self.inner.clone() }
+    }
 }
 
 impl<T: Send> WorkQueue<T> {
     /// Creates a new, empty WorkQueue with the default capacity.
     pub fn new() -> Self {
-        Self { inner: Arc::new(Mutex::new(VecDeque::new())) }
+        Self { inner: Backend::Mutex(Arc::new((Mutex::new(VecDeque::new()), Condvar::new()))) }
     }
 
     /// Creates a new, empty WorkQueue with at least the given capacity.
     pub fn with_capacity(capacity: usize) -> Self {
-        Self { inner: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))) }
+        Self {
+            inner: Backend::Mutex(Arc::new((
+                Mutex::new(VecDeque::with_capacity(capacity)),
+                Condvar::new(),
+            ))),
+        }
+    }
+
+    /// Creates a work-stealing `WorkQueue` backend, for use when the single
+    /// shared mutex of the default backend becomes a bottleneck under high
+    /// worker counts.
+    ///
+    /// Returns a `WorkQueue` handle for submitting work from outside the
+    /// pool (e.g. from a controller thread), plus one `StealingWorker` per
+    /// requested worker, each of which should be moved into its own worker
+    /// thread. A worker pulls first from its own local deque, then steals a
+    /// batch from the shared injector, then steals single items from its
+    /// peers round-robin.
+    pub fn new_stealing(num_workers: usize) -> (Self, Vec<StealingWorker<T>>) {
+        let locals: Vec<StealerDeque<T>> = (0..num_workers).map(|_| StealerDeque::new_lifo()).collect();
+        let stealers = locals.iter().map(|w| w.stealer()).collect();
+
+        let shared = Arc::new(StealingQueue {
+            injector: Injector::new(),
+            stealers,
+            notify: (Mutex::new(()), Condvar::new()),
+        });
+
+        let workers = locals
+            .into_iter()
+            .enumerate()
+            .map(|(index, local)| StealingWorker { local, shared: shared.clone(), index })
+            .collect();
+
+        (Self { inner: Backend::Stealing(shared) }, workers)
     }
 
     /// Blocks the current thread until it can check if work is available,
@@ -69,12 +146,73 @@ impl<T: Send> WorkQueue<T> {
     ///
     /// Returns `None` if there is currently no work in the queue.
     pub fn pull_work(&mut self) -> Option<T> {
-        // Try to lock the internal mutex. This will block or fail.
-        if let Ok(mut queue) = self.inner.lock() {
-            // Try to get an element from the queue
-            queue.pop_front()
+        match &self.inner {
+            Backend::Mutex(inner) => {
+                let (mut queue, _poisoned) = lock_recovering(&inner.0);
+                queue.pop_front()
+            }
+            Backend::Stealing(shared) => loop {
+                match shared.injector.steal() {
+                    Steal::Success(work) => return Some(work),
+                    Steal::Retry => continue,
+                    Steal::Empty => return None,
+                }
+            },
+        }
+    }
+
+    /// Like `pull_work`, but reports mutex poisoning instead of silently
+    /// recovering from it.
+    ///
+    /// The pull itself still goes ahead either way; `Err(WorkQueueError::Poisoned)`
+    /// means some other thread holding the queue's mutex panicked, not that
+    /// this call failed to run.
+    pub fn try_pull_work(&mut self) -> Result<Option<T>, WorkQueueError> {
+        let inner = match &self.inner {
+            Backend::Mutex(inner) => inner.clone(),
+            Backend::Stealing(_) => return Ok(self.pull_work()),
+        };
+
+        let (mut queue, poisoned) = lock_recovering(&inner.0);
+        let work = queue.pop_front();
+        if poisoned {
+            Err(WorkQueueError::Poisoned)
         } else {
-            panic!("WorkQueue::pull_work() tried to lock a poisoned mutex.");
+            Ok(work)
+        }
+    }
+
+    /// Blocks the current thread until work is available, then acquires the
+    /// work data and removes it from the queue.
+    ///
+    /// Unlike `pull_work`, this never returns `None`; it parks the calling
+    /// thread on a condition variable instead of spinning whenever the
+    /// queue is empty, waking up as soon as `push_work` adds something.
+    pub fn pull_work_blocking(&mut self) -> T {
+        let inner = match &self.inner {
+            Backend::Mutex(inner) => inner.clone(),
+            Backend::Stealing(shared) => {
+                let shared = shared.clone();
+                return loop {
+                    if let Some(w) = self.pull_work() {
+                        break w;
+                    }
+                    // Park on the stealing backend's own condvar instead of
+                    // spinning; woken by a push anywhere in the pool.
+                    shared.park(WAIT_RECHECK_INTERVAL);
+                };
+            }
+        };
+
+        let (lock, condvar) = &*inner;
+        let (mut queue, _poisoned) = lock_recovering(lock);
+        loop {
+            if let Some(w) = queue.pop_front() {
+                return w;
+            }
+            // Spurious wakeups are possible, so we loop and re-check rather
+            // than assuming work is present just because we woke up.
+            queue = condvar.wait(queue).unwrap_or_else(|poisoned| poisoned.into_inner());
         }
     }
 
@@ -82,12 +220,57 @@ impl<T: Send> WorkQueue<T> {
     /// work at the end of the queue.
     ///
     /// Returns the number of elements in the queue after inserting that work.
+    ///
+    /// When this `WorkQueue` was created by `new_stealing`, this submits the
+    /// work into the shared injector rather than a particular worker's
+    /// local deque; call `StealingWorker::push_work` from inside a worker
+    /// thread to push straight onto that worker's own deque instead. In that
+    /// case the returned length is always 0, the same as `len`.
     pub fn push_work(&mut self, work_element: T) -> usize {
-        if let Ok(mut queue) = self.inner.lock() {
-            queue.push_back(work_element);
-            queue.len()
+        match &self.inner {
+            Backend::Mutex(inner) => {
+                let (mut queue, _poisoned) = lock_recovering(&inner.0);
+                queue.push_back(work_element);
+                let len = queue.len();
+                // Drop the guard before notifying so the woken thread
+                // doesn't immediately block on the mutex we're still
+                // holding.
+                drop(queue);
+                inner.1.notify_one();
+                len
+            }
+            Backend::Stealing(shared) => {
+                shared.push(work_element);
+                // The injector doesn't expose a length; callers that need
+                // one should track it themselves, or prefer the mutex
+                // backend.
+                0
+            }
+        }
+    }
+
+    /// Like `push_work`, but reports mutex poisoning instead of silently
+    /// recovering from it.
+    ///
+    /// The push itself still goes ahead either way; `Err(WorkQueueError::Poisoned)`
+    /// means some other thread holding the queue's mutex panicked, not that
+    /// this call failed to run.
+    pub fn try_push_work(&mut self, work_element: T) -> Result<usize, WorkQueueError> {
+        let inner = match &self.inner {
+            Backend::Mutex(inner) => inner.clone(),
+            Backend::Stealing(_) => return Ok(self.push_work(work_element)),
+        };
+
+        let (mut queue, poisoned) = lock_recovering(&inner.0);
+        queue.push_back(work_element);
+        let len = queue.len();
+        drop(queue);
+        inner.1.notify_one();
+
+        if poisoned {
+            Err(WorkQueueError::Poisoned)
         } else {
-            panic!("WorkQueue::push_work() tried to lock a poisoned mutex.");
+            Ok(len)
         }
     }
 
@@ -141,30 +324,93 @@ impl<T: Send> WorkQueue<T> {
     ///
     /// 
     pub fn wait(&mut self, run_flag: &SyncFlagRx) -> Option<T> {
+        let inner = match &self.inner {
+            Backend::Mutex(inner) => inner.clone(),
+            Backend::Stealing(shared) => {
+                let shared = shared.clone();
+                while run_flag.get() {
+                    if let Some(w) = self.pull_work() {
+                        return Some(w);
+                    }
+                    // Park rather than spin; re-checks `run_flag` after
+                    // each timeout the same way the mutex backend does.
+                    shared.park(WAIT_RECHECK_INTERVAL);
+                }
+                return None;
+            }
+        };
+
+        let (lock, condvar) = &*inner;
+        let (mut queue, _poisoned) = lock_recovering(lock);
         while run_flag.get() {
-            if let Some(w) = self.pull_work() {
+            if let Some(w) = queue.pop_front() {
                 return Some(w);
             }
-            thread::yield_now();
+            // Wait with a timeout rather than forever, so we periodically
+            // wake up to re-check `run_flag` even if nobody calls
+            // `push_work` again in the meantime.
+            let (new_queue, _timeout) = condvar
+                .wait_timeout(queue, WAIT_RECHECK_INTERVAL)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            queue = new_queue;
         }
 
-        return None;
+        None
     }
 
     /// Blocks the current thread until it can examine the queue, returning the
     /// number of work elements that remain in the queue.
+    ///
+    /// Always returns 0 for a `WorkQueue` created by `new_stealing`, since
+    /// the underlying injector doesn't expose a length.
     pub fn len(&self) -> usize {
-        if let Ok(queue) = self.inner.lock() {
-            queue.len()
-        } else {
-            panic!("WorkQueue::len() tried to lock a poisoned mutex.");
+        match &self.inner {
+            Backend::Mutex(inner) => {
+                let (queue, _poisoned) = lock_recovering(&inner.0);
+                queue.len()
+            }
+            Backend::Stealing(_) => 0,
+        }
+    }
+
+    /// Like `len`, but reports mutex poisoning instead of silently
+    /// recovering from it.
+    pub fn try_len(&self) -> Result<usize, WorkQueueError> {
+        match &self.inner {
+            Backend::Mutex(inner) => {
+                let (queue, poisoned) = lock_recovering(&inner.0);
+                if poisoned {
+                    Err(WorkQueueError::Poisoned)
+                } else {
+                    Ok(queue.len())
+                }
+            }
+            Backend::Stealing(_) => Ok(0),
         }
     }
+
+    /// Returns whether the queue has no work elements left.
+    ///
+    /// Always returns `true` for a `WorkQueue` created by `new_stealing`,
+    /// since `len` can't see into the underlying injector either.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: Send> Default for WorkQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::WorkQueue;
+    use super::{Backend, WorkQueue};
+    use crate::error::WorkQueueError;
+    use std::thread;
+    use std::time::Duration;
+
     #[test]
     fn add_and_remove() {
         let mut wq: WorkQueue<i32> = WorkQueue::new();
@@ -204,4 +450,63 @@ mod tests {
             work,
         );
     }
+
+    #[test]
+    fn pull_work_blocking_wakes_on_push() {
+        let mut wq: WorkQueue<i32> = WorkQueue::new();
+        let mut producer = wq.clone();
+
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            producer.push_work(42);
+        });
+
+        // This blocks until the spawned thread's push wakes it via the
+        // condvar, rather than spinning until work shows up.
+        let work = wq.pull_work_blocking();
+        assert_eq!(
+            work, 42,
+            "Expected to pull the work item pushed by the other thread, got {}.",
+            work
+        );
+
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn recovers_from_poisoned_mutex() {
+        let mut wq: WorkQueue<i32> = WorkQueue::new();
+
+        // Simulate a worker thread crashing mid-pop: grab the backend's
+        // mutex directly and panic before releasing it.
+        let inner = match &wq.inner {
+            Backend::Mutex(inner) => inner.clone(),
+            Backend::Stealing(_) => unreachable!("WorkQueue::new() always uses the mutex backend"),
+        };
+        let result = thread::spawn(move || {
+            let _guard = inner.0.lock().unwrap();
+            panic!("simulated panic while holding the queue's mutex");
+        })
+        .join();
+        assert!(result.is_err(), "Expected the spawned thread to panic.");
+
+        assert_eq!(
+            wq.try_push_work(1),
+            Err(WorkQueueError::Poisoned),
+            "Expected try_push_work to report that the mutex was poisoned."
+        );
+        // A std::sync::Mutex has no way to un-poison itself, so every
+        // subsequent try_-call keeps reporting Poisoned even though the
+        // queue underneath has already recovered and is taking work fine.
+        assert_eq!(
+            wq.try_len(),
+            Err(WorkQueueError::Poisoned),
+            "Expected try_len to also report the mutex as poisoned."
+        );
+        assert_eq!(
+            wq.pull_work(),
+            Some(1),
+            "Expected the queue to have recovered and still contain the pushed work."
+        );
+    }
 }